@@ -11,6 +11,14 @@ pub enum BitDistribution {
     Sequential,
     /// Evenly space out the bits in the image so not all packed into top-left
     Linear { length: usize },
+    /// Automatically escalate from 1 up to `max_bit` least-significant bits
+    /// per channel, using only as many as the message needs so small
+    /// messages stay low-distortion while large ones still fit. The chosen
+    /// depth is written into a small header in the image, so `length`/
+    /// `max_bit` don't need to be supplied manually when decoding. Packs
+    /// bit-planes deterministically, bypassing the configured `Lsb`/`Rsb`
+    /// method's own bit selection.
+    Auto { max_bit: u8 },
 }
 
 impl FromStr for BitDistribution {
@@ -31,6 +39,17 @@ impl FromStr for BitDistribution {
                 });
                 Ok(Self::Linear { length })
             }
+            "auto" => {
+                let max_bit = *(parts.get(1).unwrap_or(&"4"));
+                let max_bit = max_bit.parse::<u8>().unwrap_or_else(|err| {
+                    eprintln!(
+                        "error parsing max bit depth in auto bit distribution: {}",
+                        err
+                    );
+                    std::process::exit(1);
+                });
+                Ok(Self::Auto { max_bit })
+            }
             other => Err(format!("unknown bit distribution {}", other)),
         }
     }
@@ -63,6 +82,22 @@ impl From<u8> for BitMask {
     }
 }
 
+/// Writes `bit` into bit-plane `plane` (0 = least significant) of `byte`.
+/// Used by [`BitDistribution::Auto`] to pack more than one message bit per
+/// channel, independently of the `Lsb`/`Rsb` methods below.
+pub fn write_bit_plane(byte: &mut u8, bit: u8, plane: u8) {
+    if bit == 0 {
+        *byte &= !(1 << plane);
+    } else {
+        *byte |= 1 << plane;
+    }
+}
+
+/// Reads the bit at bit-plane `plane` out of `byte`. Mirrors [`write_bit_plane`].
+pub fn read_bit_plane(byte: u8, plane: u8) -> u8 {
+    (byte >> plane) & 1
+}
+
 /// Trait for encoding a single bit of information into a byte.
 pub trait BitEncode {
     /// Encode a bit of information into a byte.
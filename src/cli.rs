@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use structopt::StructOpt;
 
 use crate::bit::BitDistribution;
+use crate::cmp::Compression;
 use crate::steg::StegMethod;
 
 #[derive(StructOpt)]
@@ -21,6 +22,12 @@ pub struct CLI {
     #[structopt(short, long)]
     pub compress: bool,
 
+    /// Compression codec to use when `--compress` is set when encoding
+    /// (deflate, lzw, packbits). Decoding picks the right codec
+    /// automatically, so this has no effect when `--decode` is set.
+    #[structopt(long, default_value = "deflate")]
+    pub codec: Compression,
+
     /// Check max message size that can be encoded with options given.
     #[structopt(short = "C", long)]
     pub check_max_length: bool,
@@ -29,7 +36,8 @@ pub struct CLI {
     #[structopt(short, long, default_value = "lsb")]
     pub method: StegMethod,
 
-    /// Method for bit distribution (sequential, linear (linear-N when decoding))
+    /// Method for bit distribution (sequential, linear (linear-N when
+    /// decoding), auto (auto-N to cap the bit-plane depth, default 4))
     #[structopt(long, default_value = "sequential")]
     pub distribution: BitDistribution,
 
@@ -41,6 +49,18 @@ pub struct CLI {
     #[structopt(short = "N", long, required_if("method", "rsb"))]
     pub max_bit: Option<u8>,
 
+    /// Use RGBA (4-channel) encoding instead of RGB, treating the alpha
+    /// channel as extra carrier capacity. Note: embedding into the
+    /// alpha-adjacent RGB of fully-transparent pixels may be visible in
+    /// viewers that discard premultiplied-away RGB data.
+    #[structopt(long)]
+    pub rgba: bool,
+
+    /// Save to a lossy output format (e.g. JPEG) even though doing so
+    /// destroys the embedded message.
+    #[structopt(long)]
+    pub force: bool,
+
     /// Output file, stdout if not present
     #[structopt(short, long, parse(from_os_str))]
     pub output: Option<PathBuf>,
@@ -61,6 +81,14 @@ impl CLI {
                 bail!(format!("max-bit must be between 1-4. Got {}", n))
             }
         }
+        if let BitDistribution::Auto { max_bit } = self.distribution {
+            if !(1..=8).contains(&max_bit) {
+                bail!(format!(
+                    "auto bit distribution's max-bit must be between 1-8. Got {}",
+                    max_bit
+                ))
+            }
+        }
         Ok(())
     }
 }
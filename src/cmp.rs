@@ -1,21 +1,166 @@
-use compression::prelude::*;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression as FlateLevel;
+use structopt::StructOpt;
+use weezl::{decode::Decoder as LzwDecoder, encode::Encoder as LzwEncoder, BitOrder};
 
 use crate::StegError;
 
-/// Compresses a slice of bytes into a new vec of bytes.
-pub fn compress(data: &[u8]) -> Result<Vec<u8>, StegError> {
-    data.iter()
-        .cloned()
-        .encode(&mut BZip2Encoder::new(9), Action::Finish)
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(StegError::Compression)
+/// Supported payload compression codecs, mirroring the kind of compression
+/// family a TIFF encoder offers (deflate, LZW, PackBits). A 1-byte tag for
+/// the codec used is stored at the start of the compressed stream so
+/// [`decompress`] can pick the matching decoder automatically.
+///
+/// There's no BZip2 option: the `compression` crate's BZip2 codec panics
+/// on an out-of-bounds bit read for ordinary, non-adversarial input, and
+/// that's true independently of anything this crate does with it.
+#[derive(StructOpt, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compression {
+    /// Deflate: general purpose, fast. The default.
+    Deflate,
+    /// LZW, as used by TIFF/GIF.
+    Lzw,
+    /// PackBits: trivial RLE, cheap for low-entropy payloads.
+    PackBits,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+    fn from_str(codec: &str) -> Result<Self, Self::Err> {
+        match codec {
+            "deflate" => Ok(Self::Deflate),
+            "lzw" => Ok(Self::Lzw),
+            "packbits" => Ok(Self::PackBits),
+            other => Err(format!("unknown compression codec: {}", other)),
+        }
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(codec: Compression) -> Self {
+        match codec {
+            Compression::Deflate => 0,
+            Compression::Lzw => 1,
+            Compression::PackBits => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = StegError;
+    fn try_from(tag: u8) -> Result<Self, Self::Error> {
+        match tag {
+            0 => Ok(Self::Deflate),
+            1 => Ok(Self::Lzw),
+            2 => Ok(Self::PackBits),
+            other => Err(StegError::Decompression(format!(
+                "unknown compression tag: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Compresses a slice of bytes with `codec`, prepending a 1-byte codec tag.
+pub fn compress(data: &[u8], codec: Compression) -> Result<Vec<u8>, StegError> {
+    let body = match codec {
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), FlateLevel::default());
+            encoder
+                .write_all(data)
+                .map_err(|e| StegError::Compression(e.to_string()))?;
+            encoder
+                .finish()
+                .map_err(|e| StegError::Compression(e.to_string()))?
+        }
+        Compression::Lzw => LzwEncoder::new(BitOrder::Msb, 8)
+            .encode(data)
+            .map_err(|e| StegError::Compression(e.to_string()))?,
+        Compression::PackBits => packbits_compress(data),
+    };
+
+    Ok([&[codec.into()], body.as_slice()].concat())
 }
 
-/// Decompresses a slice of bytes into a new vec of bytes.
+/// Decompresses a slice of bytes, reading the leading codec tag to pick the
+/// matching decoder automatically.
 pub fn decompress(data: &[u8]) -> Result<Vec<u8>, StegError> {
-    data.iter()
-        .cloned()
-        .decode(&mut BZip2Decoder::new())
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(StegError::Decompression)
-}
\ No newline at end of file
+    let (tag, body) = data
+        .split_first()
+        .ok_or_else(|| StegError::Decompression("empty compressed payload".to_string()))?;
+
+    match Compression::try_from(*tag)? {
+        Compression::Deflate => {
+            let mut out = Vec::new();
+            DeflateDecoder::new(body)
+                .read_to_end(&mut out)
+                .map_err(|e| StegError::Decompression(e.to_string()))?;
+            Ok(out)
+        }
+        Compression::Lzw => LzwDecoder::new(BitOrder::Msb, 8)
+            .decode(body)
+            .map_err(|e| StegError::Decompression(e.to_string())),
+        Compression::PackBits => packbits_decompress(body),
+    }
+}
+
+/// PackBits-style RLE: runs of 2+ repeated bytes are stored as a
+/// `257 - run` header byte followed by the repeated byte; literal runs are
+/// stored as a `len - 1` header byte followed by the literal bytes, with
+/// runs capped at 128 bytes.
+fn packbits_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let mut run = 1;
+        while i + run < data.len() && data[i + run] == data[i] && run < 128 {
+            run += 1;
+        }
+        if run >= 2 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+        } else {
+            let start = i;
+            let mut len = 1;
+            i += 1;
+            while i < data.len() && len < 128 && !(i + 1 < data.len() && data[i] == data[i + 1]) {
+                len += 1;
+                i += 1;
+            }
+            out.push((len - 1) as u8);
+            out.extend_from_slice(&data[start..start + len]);
+        }
+    }
+    out
+}
+
+/// Reverses [`packbits_compress`].
+fn packbits_decompress(data: &[u8]) -> Result<Vec<u8>, StegError> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let header = data[i] as i8;
+        i += 1;
+        if header >= 0 {
+            let len = header as usize + 1;
+            let end = i + len;
+            out.extend_from_slice(data.get(i..end).ok_or_else(|| {
+                StegError::Decompression("truncated packbits literal run".to_string())
+            })?);
+            i = end;
+        } else if header != -128 {
+            let len = (1 - i32::from(header)) as usize;
+            let byte = *data.get(i).ok_or_else(|| {
+                StegError::Decompression("truncated packbits repeat run".to_string())
+            })?;
+            i += 1;
+            out.extend(std::iter::repeat(byte).take(len));
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,25 @@
+//! Self-contained CRC-32 (IEEE 802.3) implementation used to detect corrupt
+//! or wrong-key decodes before the embedded message is handed back to the caller.
+
+/// Builds the 256-entry lookup table used by [`checksum`].
+fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for (n, slot) in table.iter_mut().enumerate() {
+        *slot = (0..8).fold(n as u32, |a, _| {
+            if a & 1 == 1 {
+                0xEDB8_8320 ^ (a >> 1)
+            } else {
+                a >> 1
+            }
+        });
+    }
+    table
+}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum of a byte slice.
+pub fn checksum(bytes: &[u8]) -> u32 {
+    let table = build_table();
+    !bytes.iter().fold(0xFFFF_FFFFu32, |a, &o| {
+        (a >> 8) ^ table[((a & 0xff) ^ u32::from(o)) as usize]
+    })
+}
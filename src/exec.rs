@@ -10,19 +10,29 @@ use atty::Stream;
 
 use crate::bit::{BitEncoder, Rsb, Lsb};
 use crate::cmp::{decompress, compress};
+use crate::fmt::is_carrier_safe;
 use crate::{cli, steg};
-use crate::steg::Steganography;
-
-fn load_rgb8_img(path: &PathBuf) -> Result<image::RgbImage> {
+use crate::steg::{CarrierImage, Steganography};
+
+/// Loads the input image as a carrier, using the alpha channel as extra
+/// carrier capacity when `rgba` is set. Note that embedding into the
+/// alpha-adjacent RGB bytes of fully-transparent pixels may be visible in
+/// viewers that discard the underlying RGB once alpha is zero, so RGBA mode
+/// is opt-in rather than the default.
+fn load_carrier_img(path: &PathBuf, rgba: bool) -> Result<CarrierImage> {
     let img = Reader::open(path)
         .context(format!("opening {:?}", path))?
         .decode()?;
-    Ok(img.into_rgb8())
+    Ok(if rgba {
+        CarrierImage::Rgba(img.into_rgba8())
+    } else {
+        CarrierImage::Rgb(img.into_rgb8())
+    })
 }
 
 /// Executes the steganography from given cli options.
 pub fn run(opt: cli::CLI) -> Result<()> {
-    let rgb8_img = load_rgb8_img(&opt.image)?;
+    let rgb8_img = load_carrier_img(&opt.image, opt.rgba)?;
 
     // create an encoder
     let mut encoder: Box<dyn Steganography> = match opt.method {
@@ -107,7 +117,7 @@ pub fn run(opt: cli::CLI) -> Result<()> {
         };
 
         if opt.compress {
-            msg = compress(&msg)?;
+            msg = compress(&msg, opt.codec)?;
         }
 
         // CHECK IF THE MESSAGE IS TOO LONG
@@ -120,6 +130,19 @@ pub fn run(opt: cli::CLI) -> Result<()> {
             );
         }
 
+        // CHECK THE OUTPUT FORMAT WON'T SILENTLY DESTROY THE EMBEDDED MESSAGE
+        if let Some(path) = &opt.output {
+            if let Ok(format) = image::ImageFormat::from_path(path) {
+                if !is_carrier_safe(format) && !opt.force {
+                    bail!(
+                        "{:?} is a lossy format and would destroy the embedded message when saved. \
+                        Use a lossless format (PNG, BMP, lossless WebP, TIFF, QOI) or pass --force to save anyway.",
+                        format
+                    );
+                }
+            }
+        }
+
         // Encode
         let res = encoder
             .encode(&rgb8_img, &msg)
@@ -0,0 +1,20 @@
+use image::ImageFormat;
+
+/// Classifies an output image format by whether saving to it would silently
+/// destroy an embedded message.
+///
+/// This is an allow-list, not a deny-list: only formats known to round-trip
+/// pixel data losslessly are considered carrier-safe, so a format the
+/// `image` crate adds support for later (or one nobody got around to
+/// checking, like GIF's 256-color palette quantization) fails safe instead
+/// of silently passing through.
+pub fn is_carrier_safe(format: ImageFormat) -> bool {
+    matches!(
+        format,
+        ImageFormat::Png
+            | ImageFormat::Bmp
+            | ImageFormat::WebP
+            | ImageFormat::Tiff
+            | ImageFormat::Qoi
+    )
+}
@@ -5,6 +5,8 @@ pub mod bit;
 pub mod cmp;
 pub mod cli;
 pub mod exec;
+pub mod crc;
+pub mod fmt;
 
 use thiserror::Error;
 
@@ -14,8 +16,12 @@ pub enum StegError {
     EncodingNotFound,
     #[error("Error decoding message: `{0}`")]
     Decoding(String),
-    #[error("Compression error")]
-    Compression(#[from] compression::prelude::CompressionError),
-    #[error("Decompression error")]
-    Decompression(#[from] compression::prelude::BZip2Error),
+    #[error("Compression error: `{0}`")]
+    Compression(String),
+    #[error("Decompression error: `{0}`")]
+    Decompression(String),
+    #[error("Checksum mismatch: decoded message is corrupt or the wrong key/settings were used")]
+    ChecksumMismatch,
+    #[error("Embedded message is truncated or incomplete")]
+    Truncated,
 }
\ No newline at end of file
@@ -7,12 +7,16 @@ fn main() {
     let opt = cli::CLI::from_args();
 
     if let Err(err) = opt.validate() {
-        eprintln!("{}", err);
+        eprintln!("{:#}", err);
         std::process::exit(1);
     }
 
     if let Err(err) = exec::run(opt) {
-        eprintln!("{}", err);
+        // `{:#}` prints the full anyhow chain ("failed to decode...: Checksum
+        // mismatch: ..."), not just the outer context - otherwise
+        // StegError::ChecksumMismatch/Truncated's Display text never reaches
+        // the user and every decode failure looks identical.
+        eprintln!("{:#}", err);
         std::process::exit(1);
     }
 }
\ No newline at end of file
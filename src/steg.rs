@@ -1,10 +1,11 @@
 use std::str::FromStr;
 
-use image::{Pixel, RgbImage};
+use image::{RgbImage, RgbaImage};
 use itertools_num::linspace;
 use structopt::StructOpt;
 
-use crate::bit::{BitEncoder, BitDistribution};
+use crate::bit::{read_bit_plane, write_bit_plane, BitDistribution, BitEncoder};
+use crate::crc;
 use crate::StegError;
 
 /// Supported steganography encoding algorithms
@@ -31,29 +32,109 @@ impl FromStr for StegMethod {
     }
 }
 
-const END: &[u8] = b"$TGV";
+/// SML-transport-style escape/start sequence. The framed bitstream begins
+/// with this sequence; any literal occurrence of it inside the payload
+/// (checked on 4-byte-aligned chunks, the same granularity it's escaped at)
+/// is escaped by doubling it, so no byte value is ever forbidden in the
+/// payload and no length needs to be known up front.
+const ESC: &[u8] = &[0x1b, 0x1b, 0x1b, 0x1b];
+
+/// Follows `ESC` to mark the end of the framed message, as opposed to an
+/// escaped literal occurrence of `ESC` (which is followed by `ESC` again).
+const END_MARK: u8 = 0x1a;
+
+/// Size in bytes of the CRC-32 checksum prepended to every embedded message.
+const CHECKSUM_LEN: usize = 4;
+
+/// Fixed framing overhead: the leading `ESC`, the trailing `ESC` + `END_MARK`
+/// + pad-count byte, and the worst-case zero padding to a 4-byte boundary.
+/// Escape doubling on top of this is payload-dependent and not accounted for.
+const FRAME_OVERHEAD: usize = ESC.len() + ESC.len() + 2 + (ESC.len() - 1);
+
+/// A carrier image, generalized over its pixel channel count so the same
+/// encoding logic works for opaque RGB images and RGBA images where the
+/// alpha channel is also used as carrier capacity (similar to how QOI's own
+/// codec is parameterized over channel count rather than having a separate
+/// decoder per pixel format).
+pub enum CarrierImage {
+    Rgb(RgbImage),
+    Rgba(RgbaImage),
+}
+
+impl CarrierImage {
+    /// Number of encodable channels per pixel (3 for RGB, 4 for RGBA).
+    pub fn channels(&self) -> u32 {
+        match self {
+            Self::Rgb(_) => 3,
+            Self::Rgba(_) => 4,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Rgb(img) => img.width(),
+            Self::Rgba(img) => img.width(),
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            Self::Rgb(img) => img.height(),
+            Self::Rgba(img) => img.height(),
+        }
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        match self {
+            Self::Rgb(img) => img.as_raw(),
+            Self::Rgba(img) => img.as_raw(),
+        }
+    }
+
+    /// Rebuilds a carrier image of the same variant from a, possibly modified,
+    /// raw pixel buffer of matching length.
+    fn with_raw(&self, raw: Vec<u8>) -> Self {
+        let (width, height) = (self.width(), self.height());
+        match self {
+            Self::Rgb(_) => Self::Rgb(RgbImage::from_raw(width, height, raw).unwrap()),
+            Self::Rgba(_) => Self::Rgba(RgbaImage::from_raw(width, height, raw).unwrap()),
+        }
+    }
+
+    /// Saves the carrier image to `path`, inferring the format from its extension.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), image::ImageError> {
+        match self {
+            Self::Rgb(img) => img.save(path),
+            Self::Rgba(img) => img.save(path),
+        }
+    }
+}
 
 /// Trait to encode a message into an image and decode a message from an image.
 pub trait Steganography {
     /// Encodes a message into an image.
-    fn encode(&mut self, img: &RgbImage, msg: &[u8]) -> Result<RgbImage, StegError>;
+    fn encode(&mut self, img: &CarrierImage, msg: &[u8]) -> Result<CarrierImage, StegError>;
     /// Decodes a message from an image.
-    fn decode(&mut self, img: &RgbImage) -> Result<Vec<u8>, StegError>;
+    fn decode(&mut self, img: &CarrierImage) -> Result<Vec<u8>, StegError>;
     /// Returns the maximum number of bytes that can be encoded into an image with the method implemented.
-    fn max_bytes(&self, img: &RgbImage) -> usize;
+    fn max_bytes(&self, img: &CarrierImage) -> usize;
 }
 
 impl Steganography for BitEncoder {
-    fn max_bytes(&self, img: &RgbImage) -> usize {
-        ((img.width() * img.height() * 3) as usize - (END.len() * 8)) / 8
+    fn max_bytes(&self, img: &CarrierImage) -> usize {
+        let total_bits = match self.bit_dist {
+            BitDistribution::Auto { max_bit } => {
+                auto_body_available_bytes(img) * max_bit as usize
+            }
+            _ => (img.width() * img.height() * img.channels()) as usize,
+        };
+        total_bits.saturating_sub((CHECKSUM_LEN + FRAME_OVERHEAD) * 8) / 8
     }
 
-    fn encode(&mut self, img: &RgbImage, msg: &[u8]) -> Result<RgbImage, StegError> {
-        let msg = if self.end_seq {
-            [msg, END].concat()
-        } else {
-            msg.to_owned()
-        };
+    fn encode(&mut self, img: &CarrierImage, msg: &[u8]) -> Result<CarrierImage, StegError> {
+        let checksum = crc::checksum(msg).to_be_bytes();
+        let payload = [&checksum, msg].concat();
+        let msg = if self.end_seq { frame(&payload) } else { payload };
 
         let mut binary_msg = String::with_capacity(msg.len() * 8);
         for byte in msg {
@@ -64,11 +145,17 @@ impl Steganography for BitEncoder {
             .map(|c| c.to_digit(10).unwrap() as u8)
             .collect();
 
-        let mut img = img.clone();
+        if let BitDistribution::Auto { max_bit } = self.bit_dist {
+            return encode_auto(img, &binary_msg, max_bit);
+        }
 
-        // generate a linear distribution from 0th to last pixel, with (number of bits to encode / 3) inbetween
-        // because in each pixel we encode 3 bits (rgb)
-        let linspace_length = (binary_msg.len() as f64 / 3.).ceil() as usize;
+        let channels = img.channels() as usize;
+        let width = img.width();
+        let mut raw = img.as_raw().to_vec();
+
+        // generate a linear distribution from 0th to last pixel, with (number of bits to encode / channels) inbetween
+        // because in each pixel we encode `channels` bits
+        let linspace_length = (binary_msg.len() as f64 / channels as f64).ceil() as usize;
         let linear_pixel_dist = get_linspace(
             0.,
             f64::from((img.width() * img.height()) - 1),
@@ -76,25 +163,33 @@ impl Steganography for BitEncoder {
         );
         let mut linear_pixel_dist = linear_pixel_dist.iter();
 
-        for (ctr, chunk) in binary_msg.chunks(3).enumerate() {
+        for (ctr, chunk) in binary_msg.chunks(channels).enumerate() {
             let (x, y) = match self.bit_dist {
                 BitDistribution::Sequential => {
-                    let x = ctr as u32 % img.width();
-                    let y = ctr as u32 / img.width();
+                    let x = ctr as u32 % width;
+                    let y = ctr as u32 / width;
                     (x, y)
                 }
                 BitDistribution::Linear { length: _ } => {
                     // SAFETY: unwrap as we create a linspace distribution based on the length of the message so we know
                     // there are enough pixels
                     let pixel_num = linear_pixel_dist.next().unwrap();
-                    let x = *pixel_num as u32 % img.width();
-                    let y = *pixel_num as u32 / img.width();
+                    let x = *pixel_num as u32 % width;
+                    let y = *pixel_num as u32 / width;
                     (x, y)
                 }
+                BitDistribution::Auto { .. } => unreachable!("handled by the early return above"),
             };
-            let pixel = img.get_pixel_mut(x, y);
+            let pixel_start = ((y * width + x) as usize) * channels;
+            if pixel_start + chunk.len() > raw.len() {
+                return Err(StegError::Decoding(
+                    "message does not fit in image; escape-sequence framing grew it past the \
+                     reported capacity"
+                        .to_string(),
+                ));
+            }
             for (idx, bit) in chunk.iter().enumerate() {
-                self.encoder.encode(bit, &mut pixel[idx]);
+                self.encoder.encode(bit, &mut raw[pixel_start + idx]);
             }
         }
 
@@ -104,30 +199,39 @@ impl Steganography for BitEncoder {
                 linspace_length
             );
         }
-        Ok(img)
+        Ok(img.with_raw(raw))
     }
 
-    fn decode(&mut self, img: &RgbImage) -> Result<Vec<u8>, StegError> {
+    fn decode(&mut self, img: &CarrierImage) -> Result<Vec<u8>, StegError> {
+        if let BitDistribution::Auto { .. } = self.bit_dist {
+            return finish_decode(decode_auto(img, self.end_seq)?);
+        }
+
         let mut bitstream: Vec<u8> = Vec::new();
 
-        let mut endstream = String::new();
-        for byte in END {
-            endstream += &format!("{:08b}", byte);
-        }
+        let channels = img.channels() as usize;
+        let width = img.width();
+        let raw = img.as_raw();
 
-        let end = endstream
-            .chars()
-            .map(|c| c.to_digit(10).unwrap() as u8)
-            .collect::<Vec<u8>>();
+        let mut payload = None;
+
+        macro_rules! push_bit {
+            ($value:expr) => {
+                bitstream.push(self.encoder.decode($value));
+                if self.end_seq && bitstream.len() % 8 == 0 {
+                    payload = unframe(&bits_to_bytes(&bitstream));
+                }
+            };
+        }
 
         match self.bit_dist {
             BitDistribution::Sequential => {
-                'outer_seq: for (_, _, pixel) in img.enumerate_pixels() {
-                    for value in pixel.channels() {
-                        if has_end(&bitstream, &end) {
+                'outer_seq: for pixel in raw.chunks(channels) {
+                    for value in pixel {
+                        push_bit!(value);
+                        if payload.is_some() {
                             break 'outer_seq;
                         }
-                        bitstream.push(self.encoder.decode(value));
                     }
                 }
             }
@@ -135,50 +239,240 @@ impl Steganography for BitEncoder {
                 let linear_pixel_dist =
                     get_linspace(0., f64::from((img.width() * img.height()) - 1), length);
                 'outer_lin: for pixel_num in linear_pixel_dist {
-                    let x = pixel_num as u32 % img.width();
-                    let y = pixel_num as u32 / img.width();
-                    let pixel = img.get_pixel(x, y);
-                    for value in pixel.channels() {
-                        if has_end(&bitstream, &end) {
+                    let x = pixel_num as u32 % width;
+                    let y = pixel_num as u32 / width;
+                    let pixel_start = ((y * width + x) as usize) * channels;
+                    for value in &raw[pixel_start..pixel_start + channels] {
+                        push_bit!(value);
+                        if payload.is_some() {
                             break 'outer_lin;
                         }
-                        bitstream.push(self.encoder.decode(value));
                     }
                 }
             }
+            BitDistribution::Auto { .. } => unreachable!("handled by the early return above"),
         }
 
-        if self.end_seq {
-            if !has_end(&bitstream, &end) {
-                return Err(StegError::EncodingNotFound);
+        let msg = if self.end_seq {
+            match payload {
+                Some(payload) => payload,
+                None => {
+                    let bytes = bits_to_bytes(&bitstream);
+                    if bytes.windows(ESC.len()).any(|w| w == ESC) {
+                        return Err(StegError::Truncated);
+                    }
+                    return Err(StegError::EncodingNotFound);
+                }
             }
+        } else {
+            bits_to_bytes(&bitstream)
+        };
+
+        finish_decode(msg)
+    }
+}
 
-            // message found in the bitstream, remove the END indicator
-            bitstream.truncate(bitstream.len() - end.len());
+/// Splits off and verifies the leading CRC-32 checksum from decoded,
+/// unframed `msg` bytes (checksum + message), shared by every bit
+/// distribution's decode path.
+fn finish_decode(msg: Vec<u8>) -> Result<Vec<u8>, StegError> {
+    if msg.len() < CHECKSUM_LEN {
+        return Err(StegError::Decoding(
+            "decoded data too short to contain a checksum".to_string(),
+        ));
+    }
+    let (checksum, msg) = msg.split_at(CHECKSUM_LEN);
+    let expected = u32::from_be_bytes(checksum.try_into().unwrap());
+    if crc::checksum(msg) != expected {
+        return Err(StegError::ChecksumMismatch);
+    }
+
+    Ok(msg.to_vec())
+}
+
+/// Frames `payload` as a self-synchronizing, length-free packet: `ESC`,
+/// the payload with any 4-byte-aligned occurrence of `ESC` doubled, zero
+/// padding out to a 4-byte boundary, then `ESC`, `END_MARK` and a final byte
+/// recording how many padding bytes were added.
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut framed = ESC.to_vec();
+    for chunk in payload.chunks(ESC.len()) {
+        framed.extend_from_slice(chunk);
+        if chunk == ESC {
+            framed.extend_from_slice(ESC);
+        }
+    }
+
+    let pad = (ESC.len() - (payload.len() % ESC.len())) % ESC.len();
+    framed.extend(std::iter::repeat(0u8).take(pad));
+    framed.extend_from_slice(ESC);
+    framed.push(END_MARK);
+    framed.push(pad as u8);
+    framed
+}
+
+/// Attempts to find and unframe a complete packet (as produced by [`frame`])
+/// within `bytes`. Returns `None` if the start sequence, or the terminator
+/// following it, hasn't been fully decoded yet.
+fn unframe(bytes: &[u8]) -> Option<Vec<u8>> {
+    let esc_len = ESC.len();
+    let start = bytes.windows(esc_len).position(|w| w == ESC)? + esc_len;
+
+    let mut out = Vec::new();
+    let mut i = start;
+    loop {
+        if bytes.len() < i + esc_len {
+            return None;
+        }
+        if &bytes[i..i + esc_len] != ESC {
+            out.extend_from_slice(&bytes[i..i + esc_len]);
+            i += esc_len;
+            continue;
+        }
+
+        if bytes.len() < i + esc_len + 1 {
+            return None;
+        }
+        if bytes[i + esc_len] == END_MARK {
+            let pad = *bytes.get(i + esc_len + 1)? as usize;
+            out.truncate(out.len().saturating_sub(pad));
+            return Some(out);
+        }
+
+        if bytes.len() < i + 2 * esc_len {
+            return None;
+        }
+        if &bytes[i + esc_len..i + 2 * esc_len] != ESC {
+            return None; // malformed framing
+        }
+        out.extend_from_slice(ESC);
+        i += 2 * esc_len;
+    }
+}
+
+/// Packs a bitstream (one bit per element, MSB first) into bytes, dropping
+/// any trailing bits that don't yet form a full byte.
+fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+    bits.chunks(8)
+        .filter(|c| c.len() == 8)
+        .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b))
+        .collect()
+}
+
+/// Number of raw bytes reserved for the `BitDistribution::Auto` depth
+/// header: one bit-plane-0 bit per byte across the first 8 raw bytes,
+/// rounded up to a whole number of pixels so the body never shares a
+/// channel byte with the header.
+fn auto_header_bytes(channels: usize) -> usize {
+    ((8 + channels - 1) / channels) * channels
+}
+
+/// Number of raw bytes available to the message body once the
+/// `BitDistribution::Auto` depth header has been reserved.
+fn auto_body_available_bytes(img: &CarrierImage) -> usize {
+    let channels = img.channels() as usize;
+    let total = (img.width() * img.height() * img.channels()) as usize;
+    total.saturating_sub(auto_header_bytes(channels))
+}
+
+/// Encodes `binary_msg` (one bit per element) using `BitDistribution::Auto`:
+/// escalates from 1 up to `max_bit` least-significant bits per channel,
+/// using only as many as the message needs, and records the chosen depth in
+/// a header so decoding doesn't need it supplied manually.
+fn encode_auto(
+    img: &CarrierImage,
+    binary_msg: &[u8],
+    max_bit: u8,
+) -> Result<CarrierImage, StegError> {
+    let channels = img.channels() as usize;
+    let mut raw = img.as_raw().to_vec();
+    let body_start = auto_header_bytes(channels);
+    let available_bytes = raw.len().saturating_sub(body_start);
+
+    let mut depth = 1u8;
+    while depth < max_bit && available_bytes.saturating_mul(depth as usize) < binary_msg.len() {
+        depth += 1;
+    }
+
+    for (i, byte) in raw.iter_mut().take(8).enumerate() {
+        let bit = (depth >> (7 - i)) & 1;
+        write_bit_plane(byte, bit, 0);
+    }
+
+    for (ctr, chunk) in binary_msg.chunks(channels * depth as usize).enumerate() {
+        let pixel_start = body_start + ctr * channels;
+        if pixel_start + channels > raw.len() {
+            return Err(StegError::Decoding(
+                "message does not fit in image even at the maximum auto bit-plane depth"
+                    .to_string(),
+            ));
         }
-        let mut msg = Vec::new();
-        for chrs in bitstream.chunks(8) {
-            let binval = u8::from_str_radix(
-                &chrs
-                    .iter()
-                    .map(|c| format! {"{}",c})
-                    .collect::<String>(),
-                2,
-            )
-            .map_err(|e| StegError::Decoding(format!("reconstructing byte: {}", e)))?;
-            msg.push(binval);
+        for (c, channel_bits) in chunk.chunks(depth as usize).enumerate() {
+            for (plane, bit) in channel_bits.iter().enumerate() {
+                write_bit_plane(&mut raw[pixel_start + c], *bit, plane as u8);
+            }
         }
-        Ok(msg)
     }
+
+    Ok(img.with_raw(raw))
 }
 
-/// helper
-pub fn has_end(bits: &[u8], end: &[u8]) -> bool {
-    if bits.len() < end.len() {
-        return false;
+/// Widest bit-plane depth [`write_bit_plane`]/[`read_bit_plane`] can address
+/// in a single byte (planes `0..8`).
+const MAX_AUTO_DEPTH: u8 = 8;
+
+/// Decodes a `BitDistribution::Auto`-encoded image, reading the bit-plane
+/// depth back out of the header written by [`encode_auto`] — the caller
+/// doesn't need to know (or guess) the `max_bit` that was used to encode,
+/// only that `auto` was used at all.
+fn decode_auto(img: &CarrierImage, end_seq: bool) -> Result<Vec<u8>, StegError> {
+    let channels = img.channels() as usize;
+    let raw = img.as_raw();
+
+    let mut depth = 0u8;
+    for byte in raw.iter().take(8) {
+        depth = (depth << 1) | read_bit_plane(*byte, 0);
     }
-    let start = bits.len() - end.len();
-    bits[start..] == end[..]
+    if depth == 0 || depth > MAX_AUTO_DEPTH {
+        return Err(StegError::Decoding(format!(
+            "invalid auto bit-plane depth header: {}",
+            depth
+        )));
+    }
+
+    let body_start = auto_header_bytes(channels);
+
+    let mut bitstream = Vec::new();
+    let mut payload = None;
+    'outer: for pixel_start in (body_start..raw.len()).step_by(channels) {
+        if pixel_start + channels > raw.len() {
+            break;
+        }
+        for value in &raw[pixel_start..pixel_start + channels] {
+            for plane in 0..depth {
+                bitstream.push(read_bit_plane(*value, plane));
+                if end_seq && bitstream.len() % 8 == 0 {
+                    payload = unframe(&bits_to_bytes(&bitstream));
+                    if payload.is_some() {
+                        break 'outer;
+                    }
+                }
+            }
+        }
+    }
+
+    if !end_seq {
+        return Ok(bits_to_bytes(&bitstream));
+    }
+
+    payload.ok_or_else(|| {
+        let bytes = bits_to_bytes(&bitstream);
+        if bytes.windows(ESC.len()).any(|w| w == ESC) {
+            StegError::Truncated
+        } else {
+            StegError::EncodingNotFound
+        }
+    })
 }
 
 /// linspace helper
@@ -186,4 +480,4 @@ pub fn get_linspace(a: f64, b: f64, n: usize) -> Vec<usize> {
     linspace(a, b, n)
         .map(|p| p.floor() as usize)
         .collect::<Vec<usize>>()
-}
\ No newline at end of file
+}